@@ -0,0 +1,83 @@
+use super::PAGE_SIZE;
+use std::collections::HashMap;
+
+/// Memoizes fetched debuggee pages, keyed by page base address.
+///
+/// Unwinders and expression evaluators often issue hundreds of tiny, adjacent reads -- e.g. while
+/// walking a call stack -- and without this, each one costs a syscall even though most land in
+/// the same handful of pages. Debuggee memory is volatile the moment the inferior runs, so the
+/// cache must be dropped on every `step`/`continue`; see [`Self::invalidate`].
+#[derive(Default)]
+pub struct PageCache {
+    pages: HashMap<usize, Vec<u8>>,
+}
+
+impl PageCache {
+    pub fn new() -> Self {
+        PageCache::default()
+    }
+
+    /// Returns the cached contents of the page containing `addr`, if present.
+    pub(crate) fn get(&self, addr: usize) -> Option<&[u8]> {
+        self.pages.get(&Self::page_base(addr)).map(Vec::as_slice)
+    }
+
+    /// Stores `page`, the full contents of the page starting at `base`.
+    ///
+    /// `base` must already be page-aligned and `page` must be exactly `*PAGE_SIZE` bytes long.
+    pub(crate) fn insert(&mut self, base: usize, page: Vec<u8>) {
+        debug_assert_eq!(base & (*PAGE_SIZE - 1), 0, "base must be page-aligned");
+        debug_assert_eq!(page.len(), *PAGE_SIZE, "page must be exactly one page long");
+        self.pages.insert(base, page);
+    }
+
+    fn page_base(addr: usize) -> usize {
+        addr & !(*PAGE_SIZE - 1)
+    }
+
+    /// Drops every cached page.
+    ///
+    /// Debuggee memory can change the moment the inferior runs again, so this must be called
+    /// whenever the target is stepped or continued.
+    pub fn invalidate(&mut self) {
+        self.pages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(fill: u8) -> Vec<u8> {
+        vec![fill; *PAGE_SIZE]
+    }
+
+    #[test]
+    fn get_misses_when_empty() {
+        let cache = PageCache::new();
+        assert!(cache.get(0).is_none());
+    }
+
+    #[test]
+    fn insert_then_get_hits_for_any_address_in_the_page() {
+        let mut cache = PageCache::new();
+        let base = *PAGE_SIZE * 3;
+        cache.insert(base, page(0xab));
+
+        assert_eq!(cache.get(base), Some(page(0xab).as_slice()));
+        assert_eq!(cache.get(base + *PAGE_SIZE - 1), Some(page(0xab).as_slice()));
+        assert!(cache.get(base + *PAGE_SIZE).is_none());
+    }
+
+    #[test]
+    fn invalidate_drops_all_pages() {
+        let mut cache = PageCache::new();
+        cache.insert(0, page(1));
+        cache.insert(*PAGE_SIZE, page(2));
+
+        cache.invalidate();
+
+        assert!(cache.get(0).is_none());
+        assert!(cache.get(*PAGE_SIZE).is_none());
+    }
+}
@@ -0,0 +1,106 @@
+use std::{error::Error, fs};
+
+use nix::unistd::Pid;
+
+/// A single mapped memory region of a process, as parsed from `/proc/<pid>/maps`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MemoryMap {
+    pub(crate) address: (usize, usize),
+    pub(crate) is_readable: bool,
+    pub(crate) is_writable: bool,
+    pub(crate) is_executable: bool,
+}
+
+impl MemoryMap {
+    fn contains(&self, addr: usize) -> bool {
+        addr >= self.address.0 && addr < self.address.1
+    }
+}
+
+/// An operation that reads or writes at a single remote memory location.
+pub(crate) trait MemoryOp {
+    fn remote_base(&self) -> usize;
+}
+
+/// Reads and parses the memory maps of the process with the given `pid` from `/proc/<pid>/maps`.
+pub(crate) fn memory_maps(pid: Pid) -> Result<Vec<MemoryMap>, Box<dyn Error>> {
+    let contents = fs::read_to_string(format!("/proc/{}/maps", pid))?;
+
+    Ok(contents.lines().filter_map(parse_map_line).collect())
+}
+
+/// Parses a single line of `/proc/<pid>/maps`, e.g.:
+/// `00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon`
+fn parse_map_line(line: &str) -> Option<MemoryMap> {
+    let mut fields = line.split_whitespace();
+
+    let mut addr_range = fields.next()?.split('-');
+    let start = usize::from_str_radix(addr_range.next()?, 16).ok()?;
+    let end = usize::from_str_radix(addr_range.next()?, 16).ok()?;
+
+    let perms = fields.next()?;
+
+    Some(MemoryMap {
+        address: (start, end),
+        is_readable: perms.starts_with('r'),
+        is_writable: perms.as_bytes().get(1) == Some(&b'w'),
+        is_executable: perms.as_bytes().get(2) == Some(&b'x'),
+    })
+}
+
+/// Splits `read_ops` into ops that fall in one of `protected_maps` and ops that don't.
+/// Returns `(protected, unprotected)`.
+pub(crate) fn split_protected<T: MemoryOp>(
+    protected_maps: &[MemoryMap],
+    read_ops: impl Iterator<Item = T>,
+) -> Result<(Vec<T>, Vec<T>), Box<dyn Error>> {
+    let mut protected = Vec::new();
+    let mut unprotected = Vec::new();
+
+    for read_op in read_ops {
+        if protected_maps
+            .iter()
+            .any(|map| map.contains(read_op.remote_base()))
+        {
+            protected.push(read_op);
+        } else {
+            unprotected.push(read_op);
+        }
+    }
+
+    Ok((protected, unprotected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_map_line_executable_mapping() {
+        let map = parse_map_line(
+            "00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon",
+        )
+        .unwrap();
+
+        assert_eq!(map.address, (0x00400000, 0x00452000));
+        assert!(map.is_readable);
+        assert!(!map.is_writable);
+        assert!(map.is_executable);
+    }
+
+    #[test]
+    fn parse_map_line_private_writable_mapping() {
+        let map = parse_map_line("7f9c8b1000-7f9c8b2000 rw-p 00000000 00:00 0").unwrap();
+
+        assert_eq!(map.address, (0x7f9c8b1000, 0x7f9c8b2000));
+        assert!(map.is_readable);
+        assert!(map.is_writable);
+        assert!(!map.is_executable);
+    }
+
+    #[test]
+    fn parse_map_line_rejects_malformed_line() {
+        assert!(parse_map_line("").is_none());
+        assert!(parse_map_line("not-a-valid-line").is_none());
+    }
+}
@@ -0,0 +1,136 @@
+mod cache;
+mod memory;
+mod pod;
+mod readmem;
+mod writemem;
+
+pub use cache::PageCache;
+pub use pod::Pod;
+pub use readmem::{ReadMemory, RemoteMemoryReader};
+pub use writemem::WriteMemory;
+
+use memory::MemoryMap;
+use nix::{sys::ptrace, unistd::Pid};
+use std::{cell::RefCell, cmp};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The page size of the current system, as reported by `sysconf(_SC_PAGESIZE)`.
+    pub(crate) static ref PAGE_SIZE: usize = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+}
+
+/// A Linux debuggee process.
+pub struct LinuxTarget {
+    pub(crate) pid: Pid,
+    page_cache: RefCell<PageCache>,
+}
+
+impl LinuxTarget {
+    pub(crate) fn new(pid: Pid) -> LinuxTarget {
+        LinuxTarget {
+            pid,
+            page_cache: RefCell::new(PageCache::new()),
+        }
+    }
+
+    /// Reads memory from the debuggee process. See [`ReadMemory`] for details.
+    pub fn read(&self) -> ReadMemory<'_> {
+        ReadMemory::new(self)
+    }
+
+    /// Returns an [`std::io::Read`] + [`std::io::Seek`] cursor over the debuggee's memory,
+    /// starting at `remote_base`. See [`RemoteMemoryReader`] for details.
+    pub fn memory_reader(&self, remote_base: usize) -> RemoteMemoryReader<'_> {
+        RemoteMemoryReader::new(self, remote_base)
+    }
+
+    /// Writes memory to the debuggee process. See [`WriteMemory`] for details.
+    pub fn write(&self) -> WriteMemory<'_> {
+        WriteMemory::new(self)
+    }
+
+    /// This target's page cache, consulted by [`ReadMemory::apply`] unless the read was marked
+    /// [`ReadMemory::fresh`]. It's invalidated automatically whenever the inferior runs again --
+    /// see [`Self::step`] and [`Self::cont`].
+    pub(crate) fn page_cache(&self) -> &RefCell<PageCache> {
+        &self.page_cache
+    }
+
+    pub(crate) fn memory_maps(&self) -> Result<Vec<MemoryMap>, Box<dyn std::error::Error>> {
+        memory::memory_maps(self.pid)
+    }
+
+    /// Single-steps the debuggee by one instruction.
+    pub fn step(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // Debuggee memory is volatile the instant the inferior is allowed to run again, so any
+        // pages we memoized before this point can no longer be trusted.
+        self.page_cache.borrow_mut().invalidate();
+        ptrace::step(self.pid, None)?;
+        nix::sys::wait::waitpid(self.pid, None)?;
+        Ok(())
+    }
+
+    /// Resumes the debuggee until it stops again (a breakpoint, a signal, or exit).
+    pub fn cont(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // Same reasoning as `step`: the cache can't outlive a resume.
+        self.page_cache.borrow_mut().invalidate();
+        ptrace::cont(self.pid, None)?;
+        nix::sys::wait::waitpid(self.pid, None)?;
+        Ok(())
+    }
+
+    /// Reads a NUL-terminated C string from the debuggee's memory at `remote_base`, without
+    /// needing to know its length up front -- useful for pulling symbol names, file paths, or
+    /// `argv` entries out of a debuggee.
+    ///
+    /// Reads one page at a time, stopping at the first `0` byte found; a page that is not yet
+    /// mapped or readable falls back to the ptrace word-reader via the usual [`ReadMemory::apply`]
+    /// fallback, so a string that ends right before an unmapped page still succeeds. `max_len`,
+    /// if given, bounds how many bytes are read before giving up on finding a terminator.
+    pub fn read_cstr(
+        &self,
+        remote_base: usize,
+        max_len: Option<usize>,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut result = Vec::new();
+        let mut addr = remote_base;
+
+        loop {
+            let to_page_boundary = *PAGE_SIZE - (addr & (*PAGE_SIZE - 1));
+            let remaining = max_len.map(|max| max.saturating_sub(result.len()));
+            let chunk_len = match remaining {
+                Some(remaining) if remaining == 0 => break,
+                Some(remaining) => cmp::min(to_page_boundary, remaining),
+                None => to_page_boundary,
+            };
+
+            let mut chunk = vec![0u8; chunk_len];
+            self.read()
+                .read_byte_slice::<u8>(&mut chunk, addr)
+                .apply()?;
+
+            match chunk.iter().position(|&byte| byte == 0) {
+                Some(pos) => {
+                    result.extend_from_slice(&chunk[..pos]);
+                    return Ok(result);
+                }
+                None => {
+                    result.extend_from_slice(&chunk);
+                    addr += chunk_len;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Like [`Self::read_cstr`], but lossily converts the result to UTF-8.
+    pub fn read_cstr_lossy(
+        &self,
+        remote_base: usize,
+        max_len: Option<usize>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(String::from_utf8_lossy(&self.read_cstr(remote_base, max_len)?).into_owned())
+    }
+}
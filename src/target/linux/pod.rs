@@ -0,0 +1,23 @@
+/// Marker trait for types whose every possible bit pattern is a valid value.
+///
+/// This is what makes [`super::readmem::ReadMemory::read_pod`] safe where [`ReadMemory::read`]
+/// is not: the debuggee can hand back arbitrary bytes (a partial write mid-read, garbage memory,
+/// a race with the inferior), and a `Pod` type is guaranteed not to care. Implement it only for
+/// types where that really holds -- plain integers, floats, and `#[repr(C)]` structs built
+/// exclusively out of other `Pod` types. Do not implement it for `bool`, enums, references, or
+/// anything else with a restricted set of valid bit patterns.
+///
+/// # Safety
+///
+/// Every bit pattern of length `size_of::<Self>()` must represent a valid value of `Self`.
+pub unsafe trait Pod: Copy {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            unsafe impl Pod for $ty {}
+        )*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
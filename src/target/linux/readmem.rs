@@ -0,0 +1,546 @@
+use super::{
+    memory::{split_protected, MemoryOp},
+    pod::Pod,
+    LinuxTarget, PAGE_SIZE,
+};
+use nix::{sys::ptrace, unistd::Pid};
+use std::{cmp, io, marker::PhantomData, mem};
+
+/// Allows to read memory from different locations in debuggee's memory as a single operation.
+pub struct ReadMemory<'a> {
+    target: &'a LinuxTarget,
+    read_ops: Vec<ReadOp>,
+    /// Skips `target`'s page cache and always fetches fresh memory. Needed by callers for whom
+    /// a stale cached page would be wrong even though the inferior hasn't visibly resumed.
+    bypass_cache: bool,
+    /// This requires a mutable reference because we rewrite values of variables in `ReadOp`.
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> ReadMemory<'a> {
+    pub(in crate::target) fn new(target: &'a LinuxTarget) -> Self {
+        ReadMemory {
+            target,
+            read_ops: Vec::new(),
+            bypass_cache: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Skips `target`'s page cache for this read, forcing a fresh fetch from the debuggee.
+    pub fn fresh(mut self) -> Self {
+        self.bypass_cache = true;
+        self
+    }
+
+    /// Reads a value of type `T` from debuggee's memory at location `remote_base`.
+    /// This value will be written to the provided variable `val`.
+    /// You should call `apply` in order to execute the memory read operation.
+    /// The provided variable `val` can't be accessed until either `apply` is called or `self` is
+    /// dropped.
+    ///
+    /// # Safety
+    ///
+    /// The type `T` must not have any invalid values.
+    /// For example, `T` must not be a `bool`, as `transmute::<u8, bool>(2)` is not a valid value for a bool.
+    /// In case of doubt, wrap the type in [`mem::MaybeUninit`].
+    // todo: further document mem safety - e.g., what happens in the case of partial read
+    pub unsafe fn read<T>(mut self, val: &'a mut T, remote_base: usize) -> Self {
+        self.read_ops.push(ReadOp {
+            remote_base,
+            len: mem::size_of::<T>(),
+            local_ptr: val as *mut T as *mut libc::c_void,
+        });
+        self
+    }
+
+    /// Reads a value of type `*mut T` from debuggee's memory at location `remote_base`.
+    /// This value will be written to the provided pointer `ptr`.
+    /// You should call `apply` in order to execute the memory read operation.
+    /// The provided pointer `ptr` can't be accessed until either `apply` is called or `self` is
+    /// dropped.
+    ///
+    /// # Safety
+    ///
+    /// Memory location at `ptr` must be of valid size and must not be outlived by `ReadMem`.
+    /// You need to ensure the lifetime guarantees, and generally you should prefer using `read<T>(&mut val)`.
+    // todo: further document mem safety - e.g., what happens in the case of partial read
+    pub unsafe fn read_ptr<T>(mut self, ptr: *mut T, remote_base: usize) -> Self {
+        self.read_ops.push(ReadOp {
+            remote_base,
+            len: mem::size_of::<T>(),
+            local_ptr: ptr as *mut _,
+        });
+        self
+    }
+
+    /// Reads a slice of type `&mut [T]` from debuggee's memory at location `remote_base`.
+    /// This value will be written to the provided slice `val`.
+    /// You should call `apply` in order to execute the memory read operation.
+    /// The provided value `val` can't be accessed until either `apply` is called or `self` is
+    /// dropped.
+    ///
+    /// # Safety
+    ///
+    /// The type `T` must not have any invalid values.
+    /// For example, `T` must not be a `bool`, as `transmute::<u8, bool>(2)` is not a valid value for a bool.
+    /// In case of doubt, wrap the type in [`mem::MaybeUninit`].
+    // todo: further document mem safety - e.g., what happens in the case of partial read
+    pub unsafe fn read_slice<T>(mut self, val: &'a mut [T], remote_base: usize) -> Self {
+        self.read_ops.push(ReadOp {
+            remote_base,
+            len: val.len() * mem::size_of::<T>(),
+            local_ptr: val.as_mut_ptr() as *mut _,
+        });
+        self
+    }
+
+    /// Reads a `u8` byte slice from debuggee's memory at location `remote_base`.
+    /// This value will be written to the provided slice `val`.
+    /// You should call `apply` in order to execute the memory read operation.
+    pub fn read_byte_slice<T>(mut self, val: &'a mut [u8], remote_base: usize) -> Self {
+        self.read_ops.push(ReadOp {
+            remote_base,
+            len: val.len(),
+            local_ptr: val.as_mut_ptr() as *mut _,
+        });
+        self
+    }
+
+    /// Reads a value of type `T` from debuggee's memory at location `remote_base`.
+    /// This value will be written to the provided variable `val`.
+    /// You should call `apply` in order to execute the memory read operation.
+    ///
+    /// Unlike [`Self::read`], this requires no `unsafe`: `T: Pod` guarantees that every bit
+    /// pattern the debuggee could hand back is a valid value of `T`.
+    pub fn read_pod<T: Pod>(self, val: &'a mut T, remote_base: usize) -> Self {
+        // Safety: `T: Pod` guarantees all bit patterns are valid values of `T`.
+        unsafe { self.read(val, remote_base) }
+    }
+
+    /// Reads a slice of type `&mut [T]` from debuggee's memory at location `remote_base`.
+    /// This value will be written to the provided slice `val`.
+    /// You should call `apply` in order to execute the memory read operation.
+    ///
+    /// Unlike [`Self::read_slice`], this requires no `unsafe`: `T: Pod` guarantees that every
+    /// bit pattern the debuggee could hand back is a valid value of `T`.
+    pub fn read_pod_slice<T: Pod>(self, val: &'a mut [T], remote_base: usize) -> Self {
+        // Safety: `T: Pod` guarantees all bit patterns are valid values of `T`.
+        unsafe { self.read_slice(val, remote_base) }
+    }
+
+    /// Executes the memory read operation.
+    pub fn apply(self) -> Result<(), Box<dyn std::error::Error>> {
+        let pid = self.target.pid;
+        let cache = if self.bypass_cache {
+            None
+        } else {
+            Some(self.target.page_cache())
+        };
+
+        // Ops that land entirely within one page can be served from (and feed back into) the
+        // page cache; everything else always takes the syscall path below. A missed page is
+        // fetched only once no matter how many of this call's ops land in it: `page_fetches`
+        // tracks, per distinct missed page, the scratch buffer it'll be read into and every op
+        // it needs to satisfy once that single read comes back.
+        let mut read_ops = Vec::with_capacity(self.read_ops.len());
+        let mut page_fetches: Vec<(usize, Vec<u8>, Vec<ReadOp>)> = Vec::new();
+        for read_op in self.read_ops {
+            if let Some(cache) = cache {
+                if read_op.fits_in_single_page() {
+                    let page_base = read_op.page_base();
+                    if let Some(page) = cache.borrow().get(page_base) {
+                        read_op.copy_from_page(page);
+                    } else {
+                        match page_fetches.iter_mut().find(|(base, ..)| *base == page_base) {
+                            Some((_, _, ops)) => ops.push(read_op),
+                            None => {
+                                page_fetches.push((page_base, vec![0u8; *PAGE_SIZE], vec![read_op]))
+                            }
+                        }
+                    }
+                    continue;
+                }
+            }
+            read_ops.push(read_op);
+        }
+        for (page_base, page, _) in &mut page_fetches {
+            read_ops.push(ReadOp {
+                remote_base: *page_base,
+                len: *PAGE_SIZE,
+                local_ptr: page.as_mut_ptr() as *mut libc::c_void,
+            });
+        }
+
+        let read_len = read_ops.iter().fold(0, |sum, read_op| sum + read_op.len);
+
+        if read_len > isize::MAX as usize {
+            panic!("Read size too big");
+        };
+
+        if !read_ops.is_empty() {
+            // FIXME: Probably a better way to do this
+            let result = Self::read_process_vm(pid, &read_ops);
+
+            if result.is_err() && result.unwrap_err() == nix::Error::Sys(nix::errno::Errno::EFAULT)
+                || result.is_ok() && result.unwrap() != read_len as isize
+            {
+                let protected_maps = self
+                    .target
+                    .memory_maps()?
+                    .into_iter()
+                    .filter(|map| !map.is_readable)
+                    .collect::<Vec<_>>();
+
+                let (protected, readable) =
+                    split_protected(&protected_maps, read_ops.into_iter())?;
+
+                // `read_ptrace` reads one word at a time and relies on each op being confined to
+                // a single page, so only the protected ops that actually take this slow path get
+                // split.
+                let mut protected_split = Vec::with_capacity(protected.len());
+                for read_op in &protected {
+                    read_op.split_on_page_boundary(&mut protected_split);
+                }
+
+                Self::read_process_vm(pid, &readable)?;
+                Self::read_ptrace(pid, &protected_split)?;
+            }
+        }
+
+        if let Some(cache) = cache {
+            let mut cache = cache.borrow_mut();
+            for (page_base, page, ops) in page_fetches {
+                for read_op in &ops {
+                    read_op.copy_from_page(&page);
+                }
+                cache.insert(page_base, page);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Allows to read from several different locations with one system call.
+    /// It will error on pages that are not readable. Returns number of bytes read at granularity of ReadOps.
+    ///
+    /// `process_vm_readv` caps the number of iovecs it will accept in a single call at
+    /// `IOV_MAX`, so a scatter read spanning more ops than that is issued as several syscalls.
+    fn read_process_vm(pid: Pid, read_ops: &[ReadOp]) -> Result<isize, nix::Error> {
+        let mut bytes_read: isize = 0;
+
+        for chunk in read_ops.chunks(libc::IOV_MAX as usize) {
+            let remote_iov = chunk
+                .iter()
+                .map(|read_op| read_op.as_remote_iovec())
+                .collect::<Vec<_>>();
+
+            let local_iov = chunk
+                .iter()
+                .map(|read_op| read_op.as_local_iovec())
+                .collect::<Vec<_>>();
+
+            let chunk_bytes_read = unsafe {
+                // todo: document unsafety
+                libc::process_vm_readv(
+                    pid.into(),
+                    local_iov.as_ptr(),
+                    local_iov.len() as libc::c_ulong,
+                    remote_iov.as_ptr(),
+                    remote_iov.len() as libc::c_ulong,
+                    0,
+                )
+            };
+
+            if chunk_bytes_read == -1 {
+                return Err(nix::Error::last());
+            }
+
+            bytes_read += chunk_bytes_read;
+        }
+
+        Ok(bytes_read)
+    }
+
+    /// Allows to read from protected memory pages.
+    /// This operation results in multiple system calls and is inefficient.
+    fn read_ptrace(pid: Pid, read_ops: &[ReadOp]) -> Result<(), Box<dyn std::error::Error>> {
+        let long_size = std::mem::size_of::<std::os::raw::c_long>();
+
+        for read_op in read_ops {
+            let mut offset: usize = 0;
+            // Read until all of the data is read
+            while offset < read_op.len {
+                let data =
+                    ptrace::read(pid, (read_op.remote_base + offset) as *mut std::ffi::c_void)?;
+
+                // Read full word. No need to preserve other data
+                if (read_op.len - offset) >= long_size {
+                    // todo: document unsafety
+                    unsafe {
+                        *((read_op.local_ptr as usize + offset) as *mut i64) = data;
+                    }
+
+                // Read part smaller than word. Need to preserve other data
+                } else {
+                    // todo: document unsafety
+                    unsafe {
+                        let previous_bytes: &mut [u8] = std::slice::from_raw_parts_mut(
+                            (read_op.local_ptr as usize + offset) as *mut u8,
+                            read_op.len - offset,
+                        );
+                        let data_bytes = data.to_ne_bytes();
+
+                        previous_bytes[0..(read_op.len - offset)]
+                            .clone_from_slice(&data_bytes[0..(read_op.len - offset)]);
+                    }
+                }
+                offset += long_size;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A cursor-based [`std::io::Read`] + [`std::io::Seek`] adapter over a debuggee's memory.
+///
+/// Unlike [`ReadMemory`], which needs every field sized into the builder up front, this lets
+/// callers drive `byteorder`, `gimli`, `serde`, or any other `Read`-based parser directly against
+/// live process memory. Each `read` call issues a fresh [`ReadMemory`] operation (with the usual
+/// ptrace fallback for protected pages) starting at the current cursor and advances it by the
+/// number of bytes transferred.
+pub struct RemoteMemoryReader<'a> {
+    target: &'a LinuxTarget,
+    cursor: usize,
+}
+
+impl<'a> RemoteMemoryReader<'a> {
+    /// Creates a reader over `target`'s memory, starting at `remote_base`.
+    pub fn new(target: &'a LinuxTarget, remote_base: usize) -> Self {
+        RemoteMemoryReader {
+            target,
+            cursor: remote_base,
+        }
+    }
+
+    /// Maps a failure from [`ReadMemory::apply`] to the `io::Error` it represents, treating an
+    /// unreadable address (`EFAULT`) or a short read as an unexpected end of the debuggee's
+    /// memory rather than a generic I/O failure.
+    fn map_read_err(err: Box<dyn std::error::Error>) -> io::Error {
+        let is_efault = err
+            .downcast_ref::<nix::Error>()
+            .map(|nix_err| *nix_err == nix::Error::Sys(nix::errno::Errno::EFAULT))
+            .unwrap_or(false);
+
+        let kind = if is_efault {
+            io::ErrorKind::UnexpectedEof
+        } else {
+            io::ErrorKind::Other
+        };
+        io::Error::new(kind, err.to_string())
+    }
+}
+
+impl<'a> io::Read for RemoteMemoryReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let len = buf.len();
+        ReadMemory::new(self.target)
+            .read_byte_slice::<u8>(buf, self.cursor)
+            .apply()
+            .map_err(Self::map_read_err)?;
+
+        self.cursor += len;
+        Ok(len)
+    }
+}
+
+impl<'a> io::Seek for RemoteMemoryReader<'a> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let new_cursor = match pos {
+            io::SeekFrom::Start(offset) => offset as i64,
+            io::SeekFrom::Current(offset) => self.cursor as i64 + offset,
+            io::SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "seeking relative to the end of debuggee memory is not supported",
+                ))
+            }
+        };
+
+        if new_cursor < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative remote address",
+            ));
+        }
+
+        self.cursor = new_cursor as usize;
+        Ok(self.cursor as u64)
+    }
+}
+
+/// A single memory read operation.
+#[derive(Debug, Clone, Copy)]
+struct ReadOp {
+    // Remote memory location.
+    remote_base: usize,
+    // Size of the `local_ptr` buffer.
+    len: usize,
+    // Pointer to a local destination buffer.
+    local_ptr: *mut libc::c_void,
+}
+
+impl MemoryOp for ReadOp {
+    fn remote_base(&self) -> usize {
+        self.remote_base
+    }
+}
+
+impl ReadOp {
+    /// Converts the memory read operation into a remote IoVec.
+    fn as_remote_iovec(&self) -> libc::iovec {
+        libc::iovec {
+            iov_base: self.remote_base as *const libc::c_void as *mut _,
+            iov_len: self.len,
+        }
+    }
+
+    /// Converts the memory read operation into a local IoVec.
+    fn as_local_iovec(&self) -> libc::iovec {
+        libc::iovec {
+            iov_base: self.local_ptr,
+            iov_len: self.len,
+        }
+    }
+
+    /// The base address of the page `remote_base` falls in.
+    fn page_base(&self) -> usize {
+        self.remote_base & !(*PAGE_SIZE - 1)
+    }
+
+    /// Returns `true` if this read does not cross a page boundary, i.e. it can be served by a
+    /// single `ptrace` page without splitting, and is therefore eligible to be served out of
+    /// (and to populate) the page cache.
+    fn fits_in_single_page(&self) -> bool {
+        let page_start = self.remote_base & !(*PAGE_SIZE - 1);
+        let last_byte = self.remote_base + self.len.saturating_sub(1);
+        last_byte < page_start + *PAGE_SIZE
+    }
+
+    /// Copies this op's bytes out of `page`, the full, cached contents of the page it falls in.
+    fn copy_from_page(&self, page: &[u8]) {
+        let offset = self.remote_base - self.page_base();
+        // Safety: `local_ptr` is valid for `len` bytes for the lifetime of the borrowed `ReadOp`,
+        // as guaranteed by `ReadMemory::read`/`read_slice`/`read_byte_slice`.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                page[offset..offset + self.len].as_ptr(),
+                self.local_ptr as *mut u8,
+                self.len,
+            );
+        }
+    }
+
+    /// Splits ReadOp so that each resulting ReadOp resides in only one memory page.
+    fn split_on_page_boundary(&self, out: &mut Vec<ReadOp>) {
+        if self.fits_in_single_page() {
+            out.push(*self);
+            return;
+        }
+
+        // Number of bytes left to be read
+        let mut left = self.len;
+
+        let next_page_distance = *PAGE_SIZE - ((*PAGE_SIZE - 1) & self.remote_base);
+        let to_next_read_op = cmp::min(left, next_page_distance);
+        // Read from remote_base to the end or to the next page
+        out.push(ReadOp {
+            remote_base: self.remote_base,
+            len: to_next_read_op,
+            local_ptr: self.local_ptr,
+        });
+        left -= to_next_read_op;
+
+        while left > 0 {
+            if left < *PAGE_SIZE {
+                // Read from beginning of the page to a part in the middle (last read)
+                out.push(ReadOp {
+                    remote_base: self.remote_base + (self.len - left),
+                    len: left,
+                    local_ptr: (self.local_ptr as usize + (self.len - left)) as *mut libc::c_void,
+                });
+                break;
+            } else {
+                // Whole page is being read
+                out.push(ReadOp {
+                    remote_base: self.remote_base + (self.len - left),
+                    len: *PAGE_SIZE,
+                    local_ptr: (self.local_ptr as usize + (self.len - left)) as *mut libc::c_void,
+                });
+                left -= *PAGE_SIZE;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(remote_base: usize, len: usize) -> ReadOp {
+        ReadOp {
+            remote_base,
+            len,
+            local_ptr: std::ptr::null_mut(),
+        }
+    }
+
+    #[test]
+    fn fits_in_single_page_within_one_page() {
+        assert!(op(0, *PAGE_SIZE).fits_in_single_page());
+        assert!(op(*PAGE_SIZE - 8, 8).fits_in_single_page());
+    }
+
+    #[test]
+    fn fits_in_single_page_crossing_boundary() {
+        assert!(!op(*PAGE_SIZE - 4, 8).fits_in_single_page());
+    }
+
+    #[test]
+    fn fits_in_single_page_zero_length() {
+        assert!(op(*PAGE_SIZE - 1, 0).fits_in_single_page());
+    }
+
+    #[test]
+    fn split_on_page_boundary_noop_when_already_single_page() {
+        let mut out = Vec::new();
+        op(0, *PAGE_SIZE / 2).split_on_page_boundary(&mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].remote_base, 0);
+        assert_eq!(out[0].len, *PAGE_SIZE / 2);
+    }
+
+    #[test]
+    fn split_on_page_boundary_splits_at_each_page() {
+        let mut out = Vec::new();
+        // Starts 4 bytes before a page boundary and spans almost three pages.
+        let remote_base = *PAGE_SIZE - 4;
+        let len = *PAGE_SIZE * 2 + 8;
+        op(remote_base, len).split_on_page_boundary(&mut out);
+
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0].remote_base, remote_base);
+        assert_eq!(out[0].len, 4);
+        assert_eq!(out[1].remote_base, remote_base + 4);
+        assert_eq!(out[1].len, *PAGE_SIZE);
+        assert_eq!(out[2].remote_base, remote_base + 4 + *PAGE_SIZE);
+        assert_eq!(out[2].len, 4);
+
+        let total: usize = out.iter().map(|op| op.len).sum();
+        assert_eq!(total, len);
+    }
+}
@@ -0,0 +1,260 @@
+use super::{memory::MemoryOp, LinuxTarget, PAGE_SIZE};
+use nix::{sys::ptrace, unistd::Pid};
+use std::{marker::PhantomData, mem};
+
+/// Allows to write memory to different locations in debuggee's memory as a single operation.
+pub struct WriteMemory<'a> {
+    target: &'a LinuxTarget,
+    write_ops: Vec<WriteOp>,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> WriteMemory<'a> {
+    pub(in crate::target) fn new(target: &'a LinuxTarget) -> Self {
+        WriteMemory {
+            target,
+            write_ops: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Writes a value of type `T` to debuggee's memory at location `remote_base`.
+    /// You should call `apply` in order to execute the memory write operation.
+    pub fn write<T>(mut self, val: &'a T, remote_base: usize) -> Self {
+        self.write_ops.push(WriteOp {
+            remote_base,
+            len: mem::size_of::<T>(),
+            local_ptr: val as *const T as *const libc::c_void,
+        });
+        self
+    }
+
+    /// Writes a slice of type `&[T]` to debuggee's memory at location `remote_base`.
+    /// You should call `apply` in order to execute the memory write operation.
+    pub fn write_slice<T>(mut self, val: &'a [T], remote_base: usize) -> Self {
+        self.write_ops.push(WriteOp {
+            remote_base,
+            len: val.len() * mem::size_of::<T>(),
+            local_ptr: val.as_ptr() as *const libc::c_void,
+        });
+        self
+    }
+
+    /// Executes the memory write operation.
+    ///
+    /// Each op is first attempted with a plain `process_vm_writev`, which only succeeds against
+    /// mappings that already have write permission -- e.g. this fails for `.text`, which is
+    /// commonly the target when patching in a breakpoint. For those, the mapping's protection is
+    /// temporarily widened with `mprotect` (preserving its other flags exactly), the write is
+    /// retried, and the original protection is restored immediately after. If even changing
+    /// protection fails, the write falls back to `ptrace::write`, which -- like the read path's
+    /// ptrace fallback -- can reach a page regardless of its declared permissions, just at the
+    /// cost of one syscall per word.
+    pub fn apply(self) -> Result<(), Box<dyn std::error::Error>> {
+        let pid = self.target.pid;
+
+        for write_op in &self.write_ops {
+            if Self::write_process_vm(pid, write_op).is_ok() {
+                continue;
+            }
+
+            match self.write_with_mprotect(write_op) {
+                Ok(()) => continue,
+                Err(_) => Self::write_ptrace(pid, write_op)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `write_op` in one shot via `process_vm_writev`. Fails if any touched page lacks
+    /// write permission.
+    fn write_process_vm(pid: Pid, write_op: &WriteOp) -> Result<(), nix::Error> {
+        let remote_iov = write_op.as_remote_iovec();
+        let local_iov = write_op.as_local_iovec();
+
+        let bytes_written = unsafe {
+            // todo: document unsafety
+            libc::process_vm_writev(pid.into(), &local_iov, 1, &remote_iov, 1, 0)
+        };
+
+        if bytes_written == -1 {
+            return Err(nix::Error::last());
+        }
+        if bytes_written as usize != write_op.len {
+            return Err(nix::Error::Sys(nix::errno::Errno::EIO));
+        }
+        Ok(())
+    }
+
+    /// Temporarily widens the protection of the page range covering `write_op` to include write
+    /// permission, retries the write, then restores the range's original protection exactly.
+    ///
+    /// The write and the restore are two independent outcomes: a failure to restore must not be
+    /// allowed to hide a write that actually landed, so both results are tracked separately
+    /// rather than threading the restore call's `?` straight through a write that already
+    /// succeeded.
+    fn write_with_mprotect(&self, write_op: &WriteOp) -> Result<(), Box<dyn std::error::Error>> {
+        let pid = self.target.pid;
+
+        let page_start = write_op.remote_base & !(*PAGE_SIZE - 1);
+        let page_end = (write_op.remote_base + write_op.len + *PAGE_SIZE - 1) & !(*PAGE_SIZE - 1);
+        let region_len = page_end - page_start;
+
+        let map = self
+            .target
+            .memory_maps()?
+            .into_iter()
+            .find(|map| page_start >= map.address.0 && page_end <= map.address.1)
+            .ok_or("no mapping covers the write target")?;
+
+        let mut prot = 0;
+        if map.is_readable {
+            prot |= libc::PROT_READ;
+        }
+        if map.is_writable {
+            prot |= libc::PROT_WRITE;
+        }
+        if map.is_executable {
+            prot |= libc::PROT_EXEC;
+        }
+
+        remote_mprotect(pid, page_start, region_len, prot | libc::PROT_WRITE)?;
+        let write_result = Self::write_process_vm(pid, write_op).map_err(Box::from);
+        let restore_result = remote_mprotect(pid, page_start, region_len, prot);
+
+        match (write_result, restore_result) {
+            (Ok(()), Ok(())) => Ok(()),
+            (Ok(()), Err(err)) => Err(format!(
+                "write succeeded but failed to restore the original page protection: {}",
+                err
+            )
+            .into()),
+            (Err(err), _) => Err(err),
+        }
+    }
+
+    /// Writes `write_op` one word at a time via `ptrace::write`, which can reach a page
+    /// regardless of its protection.
+    fn write_ptrace(pid: Pid, write_op: &WriteOp) -> Result<(), Box<dyn std::error::Error>> {
+        let long_size = mem::size_of::<std::os::raw::c_long>();
+        let mut offset = 0;
+
+        while offset < write_op.len {
+            let remaining = write_op.len - offset;
+            let addr = (write_op.remote_base + offset) as *mut std::ffi::c_void;
+
+            let data = if remaining >= long_size {
+                unsafe { *((write_op.local_ptr as usize + offset) as *const i64) }
+            } else {
+                // Only part of this word is ours to overwrite; preserve the rest of the
+                // debuggee's existing bytes around it.
+                let existing = ptrace::read(pid, addr)?;
+                let mut bytes = existing.to_ne_bytes();
+                let new_bytes: &[u8] = unsafe {
+                    std::slice::from_raw_parts(
+                        (write_op.local_ptr as usize + offset) as *const u8,
+                        remaining,
+                    )
+                };
+                bytes[0..remaining].clone_from_slice(new_bytes);
+                i64::from_ne_bytes(bytes)
+            };
+
+            unsafe {
+                ptrace::write(pid, addr, data as *mut std::ffi::c_void)?;
+            }
+            offset += long_size;
+        }
+        Ok(())
+    }
+}
+
+/// A single memory write operation.
+#[derive(Debug, Clone, Copy)]
+struct WriteOp {
+    // Remote memory location.
+    remote_base: usize,
+    // Size of the `local_ptr` buffer.
+    len: usize,
+    // Pointer to the local source buffer.
+    local_ptr: *const libc::c_void,
+}
+
+impl MemoryOp for WriteOp {
+    fn remote_base(&self) -> usize {
+        self.remote_base
+    }
+}
+
+impl WriteOp {
+    /// Converts the memory write operation into a remote IoVec.
+    fn as_remote_iovec(&self) -> libc::iovec {
+        libc::iovec {
+            iov_base: self.remote_base as *const libc::c_void as *mut _,
+            iov_len: self.len,
+        }
+    }
+
+    /// Converts the memory write operation into a local IoVec.
+    fn as_local_iovec(&self) -> libc::iovec {
+        libc::iovec {
+            iov_base: self.local_ptr as *mut _,
+            iov_len: self.len,
+        }
+    }
+}
+
+/// Changes the protection of `[addr, addr + len)` in the debuggee to `prot` by injecting an
+/// `mprotect` syscall via ptrace: the debuggee's registers and the instruction at its current
+/// `rip` are saved, a `syscall` is executed in its place, and both are restored once the result
+/// comes back. `addr` must already be page-aligned and `len` a multiple of the page size.
+fn remote_mprotect(
+    pid: Pid,
+    addr: usize,
+    len: usize,
+    prot: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let saved_regs = ptrace::getregs(pid)?;
+    let mut call_regs = saved_regs;
+    call_regs.rax = libc::SYS_mprotect as u64;
+    call_regs.rdi = addr as u64;
+    call_regs.rsi = len as u64;
+    call_regs.rdx = prot as u64;
+    call_regs.rip = saved_regs.rip;
+
+    let saved_insn = ptrace::read(pid, saved_regs.rip as *mut std::ffi::c_void)?;
+    // `syscall` (0x0f 0x05) immediately followed by a breakpoint (0xcc) to regain control.
+    let mut patched_insn = saved_insn.to_ne_bytes();
+    patched_insn[0] = 0x0f;
+    patched_insn[1] = 0x05;
+    patched_insn[2] = 0xcc;
+    unsafe {
+        ptrace::write(
+            pid,
+            saved_regs.rip as *mut std::ffi::c_void,
+            i64::from_ne_bytes(patched_insn) as *mut std::ffi::c_void,
+        )?;
+    }
+
+    ptrace::setregs(pid, call_regs)?;
+    ptrace::cont(pid, None)?;
+    nix::sys::wait::waitpid(pid, None)?;
+
+    let result_regs = ptrace::getregs(pid)?;
+
+    // Restore the original instruction and registers regardless of the syscall's outcome.
+    unsafe {
+        ptrace::write(
+            pid,
+            saved_regs.rip as *mut std::ffi::c_void,
+            saved_insn as *mut std::ffi::c_void,
+        )?;
+    }
+    ptrace::setregs(pid, saved_regs)?;
+
+    let ret = result_regs.rax as i64;
+    if ret < 0 {
+        return Err(format!("remote mprotect failed: errno {}", -ret).into());
+    }
+    Ok(())
+}